@@ -1,4 +1,4 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 
 use axum::{
     http::StatusCode,
@@ -9,6 +9,8 @@ use indexify_utils::get_epoch_time_in_ms;
 use serde::{Deserialize, Serialize};
 use utoipa::ToSchema;
 
+use crate::ids::{GraphName, InvocationRef, NamespaceName, OutputId, TaskId};
+
 #[derive(Debug, ToSchema)]
 pub struct IndexifyAPIError {
     status_code: StatusCode,
@@ -42,6 +44,14 @@ impl IndexifyAPIError {
     pub fn bad_request(message: &str) -> Self {
         Self::new(StatusCode::BAD_REQUEST, message)
     }
+
+    pub fn unauthorized(message: &str) -> Self {
+        Self::new(StatusCode::UNAUTHORIZED, message)
+    }
+
+    pub fn forbidden(message: &str) -> Self {
+        Self::new(StatusCode::FORBIDDEN, message)
+    }
 }
 
 impl IntoResponse for IndexifyAPIError {
@@ -57,22 +67,110 @@ impl From<serde_json::Error> for IndexifyAPIError {
     }
 }
 
-#[derive(Debug, Serialize, Deserialize, ToSchema)]
+/// Comparison applied between a `FilterClause`'s `value` and the named
+/// field on a listed resource.
+#[derive(Debug, Serialize, Deserialize, ToSchema, Clone, Copy, PartialEq, Eq)]
+pub enum FilterOperator {
+    Eq,
+    NotEq,
+    Gt,
+    Gte,
+    Lt,
+    Lte,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema, Clone)]
+pub struct FilterClause {
+    pub field: String,
+    pub operator: FilterOperator,
+    pub value: serde_json::Value,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema, Clone, Copy, PartialEq, Eq)]
+pub enum SortDir {
+    Asc,
+    Desc,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema, Clone)]
+pub struct SortClause {
+    pub field: String,
+    pub direction: SortDir,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema, Default)]
 pub struct ListParams {
     pub limit: Option<usize>,
     pub cursor: Option<Vec<u8>>,
+    #[serde(default)]
+    pub filters: Vec<FilterClause>,
+    pub sort: Option<SortClause>,
+}
+
+impl ListParams {
+    /// Confirms every filtered/sorted field is in `allowed_fields`, the
+    /// per-resource-type allowlist of columns a caller may query on.
+    /// Returns a precise `bad_request` naming the offending field.
+    pub fn validate_fields(&self, allowed_fields: &[&str]) -> Result<(), IndexifyAPIError> {
+        for clause in &self.filters {
+            if !allowed_fields.contains(&clause.field.as_str()) {
+                return Err(IndexifyAPIError::bad_request(&format!(
+                    "cannot filter on unknown field '{}'",
+                    clause.field
+                )));
+            }
+        }
+        if let Some(sort) = &self.sort {
+            if !allowed_fields.contains(&sort.field.as_str()) {
+                return Err(IndexifyAPIError::bad_request(&format!(
+                    "cannot sort on unknown field '{}'",
+                    sort.field
+                )));
+            }
+        }
+        Ok(())
+    }
+
+    /// Serializes these params into a query string, e.g.
+    /// `limit=10&filters=%5B...%5D&sort_by=outcome&sort_dir=desc`, mirroring
+    /// the way Docker-client crates serialize `ServiceListOptions` filter
+    /// maps into the list query.
+    pub fn to_query_string(&self) -> Result<String, IndexifyAPIError> {
+        let mut pairs: Vec<(&str, String)> = Vec::new();
+        if let Some(limit) = self.limit {
+            pairs.push(("limit", limit.to_string()));
+        }
+        if let Some(cursor) = &self.cursor {
+            pairs.push(("cursor", hex::encode(cursor)));
+        }
+        if !self.filters.is_empty() {
+            pairs.push(("filters", serde_json::to_string(&self.filters)?));
+        }
+        if let Some(sort) = &self.sort {
+            pairs.push(("sort_by", sort.field.clone()));
+            pairs.push((
+                "sort_dir",
+                match sort.direction {
+                    SortDir::Asc => "asc".to_string(),
+                    SortDir::Desc => "desc".to_string(),
+                },
+            ));
+        }
+        serde_urlencoded::to_string(pairs)
+            .map_err(|e| IndexifyAPIError::internal_error_str(&e.to_string()))
+    }
 }
 
 #[derive(Debug, Serialize, Deserialize, ToSchema)]
 pub struct Namespace {
-    name: String,
+    name: NamespaceName,
     created_at: u64,
 }
 
 impl From<data_model::Namespace> for Namespace {
     fn from(namespace: data_model::Namespace) -> Self {
         Self {
-            name: namespace.name,
+            name: namespace.name.into(),
             created_at: namespace.created_at,
         }
     }
@@ -83,12 +181,80 @@ pub struct NamespaceList {
     pub namespaces: Vec<Namespace>,
 }
 
+/// Comparison applied between a `Constraint`'s `values` and the value of a
+/// matching label on an `ExecutorMetadata`.
+#[derive(Debug, Serialize, Deserialize, ToSchema, Clone, PartialEq, Eq)]
+pub enum ConstraintOperator {
+    Eq,
+    NotEq,
+    In,
+    NotIn,
+    Exists,
+}
+
+/// A single label-selector constraint, e.g. `gpu in [a100, h100]` or
+/// `region == us-east`, matched against the `labels` an executor advertises
+/// in its `ExecutorMetadata`.
+#[derive(Debug, Serialize, Deserialize, ToSchema, Clone)]
+pub struct Constraint {
+    pub key: String,
+    pub operator: ConstraintOperator,
+    #[serde(default)]
+    pub values: Vec<String>,
+}
+
+impl From<Constraint> for data_model::Constraint {
+    fn from(val: Constraint) -> Self {
+        data_model::Constraint {
+            key: val.key,
+            operator: val.operator.into(),
+            values: val.values,
+        }
+    }
+}
+
+impl From<data_model::Constraint> for Constraint {
+    fn from(c: data_model::Constraint) -> Self {
+        Self {
+            key: c.key,
+            operator: c.operator.into(),
+            values: c.values,
+        }
+    }
+}
+
+impl From<ConstraintOperator> for data_model::ConstraintOperator {
+    fn from(val: ConstraintOperator) -> Self {
+        match val {
+            ConstraintOperator::Eq => data_model::ConstraintOperator::Eq,
+            ConstraintOperator::NotEq => data_model::ConstraintOperator::NotEq,
+            ConstraintOperator::In => data_model::ConstraintOperator::In,
+            ConstraintOperator::NotIn => data_model::ConstraintOperator::NotIn,
+            ConstraintOperator::Exists => data_model::ConstraintOperator::Exists,
+        }
+    }
+}
+
+impl From<data_model::ConstraintOperator> for ConstraintOperator {
+    fn from(op: data_model::ConstraintOperator) -> Self {
+        match op {
+            data_model::ConstraintOperator::Eq => ConstraintOperator::Eq,
+            data_model::ConstraintOperator::NotEq => ConstraintOperator::NotEq,
+            data_model::ConstraintOperator::In => ConstraintOperator::In,
+            data_model::ConstraintOperator::NotIn => ConstraintOperator::NotIn,
+            data_model::ConstraintOperator::Exists => ConstraintOperator::Exists,
+        }
+    }
+}
+
 #[derive(Debug, Serialize, Deserialize, ToSchema, Clone)]
 pub struct ComputeFn {
     pub name: String,
     pub fn_name: String,
     pub description: String,
     pub reducer: bool,
+    #[serde(default)]
+    pub placement_constraints: Vec<Constraint>,
 }
 
 impl From<&ComputeFn> for data_model::ComputeFn {
@@ -97,7 +263,12 @@ impl From<&ComputeFn> for data_model::ComputeFn {
             name: val.name.clone(),
             fn_name: val.fn_name.clone(),
             description: val.description.clone(),
-            placement_constraints: Default::default(),
+            placement_constraints: val
+                .placement_constraints
+                .iter()
+                .cloned()
+                .map(Into::into)
+                .collect(),
             reducer: val.reducer,
         }
     }
@@ -109,7 +280,12 @@ impl From<ComputeFn> for data_model::ComputeFn {
             name: val.name.clone(),
             fn_name: val.fn_name.clone(),
             description: val.description.clone(),
-            placement_constraints: Default::default(),
+            placement_constraints: val
+                .placement_constraints
+                .iter()
+                .cloned()
+                .map(Into::into)
+                .collect(),
             reducer: val.reducer,
         }
     }
@@ -122,6 +298,11 @@ impl From<data_model::ComputeFn> for ComputeFn {
             fn_name: c.fn_name,
             description: c.description,
             reducer: c.reducer,
+            placement_constraints: c
+                .placement_constraints
+                .into_iter()
+                .map(Into::into)
+                .collect(),
         }
     }
 }
@@ -132,6 +313,8 @@ pub struct DynamicRouter {
     pub source_fn: String,
     pub description: String,
     pub target_fns: Vec<String>,
+    #[serde(default)]
+    pub placement_constraints: Vec<Constraint>,
 }
 
 impl From<DynamicRouter> for data_model::DynamicEdgeRouter {
@@ -141,6 +324,11 @@ impl From<DynamicRouter> for data_model::DynamicEdgeRouter {
             source_fn: val.source_fn.clone(),
             description: val.description.clone(),
             target_functions: val.target_fns.clone(),
+            placement_constraints: val
+                .placement_constraints
+                .into_iter()
+                .map(Into::into)
+                .collect(),
         }
     }
 }
@@ -152,6 +340,11 @@ impl From<data_model::DynamicEdgeRouter> for DynamicRouter {
             source_fn: d.source_fn,
             description: d.description,
             target_fns: d.target_functions,
+            placement_constraints: d
+                .placement_constraints
+                .into_iter()
+                .map(Into::into)
+                .collect(),
         }
     }
 }
@@ -193,8 +386,8 @@ impl From<data_model::Node> for Node {
 
 #[derive(Debug, Serialize, Deserialize, ToSchema)]
 pub struct ComputeGraph {
-    pub name: String,
-    pub namespace: String,
+    pub name: GraphName,
+    pub namespace: NamespaceName,
     pub description: String,
     pub start_node: Node,
     pub nodes: HashMap<String, Node>,
@@ -204,20 +397,146 @@ pub struct ComputeGraph {
 }
 
 impl ComputeGraph {
+    /// Checks that the graph is well formed before it's persisted: the
+    /// start node and every edge endpoint must reference a node present in
+    /// `nodes`, every `DynamicRouter`'s `target_fns` must exist, the edge
+    /// graph must be acyclic, and every node must be reachable from
+    /// `start_node`. Each failure names the offending node or edge.
+    pub fn validate(&self) -> Result<(), IndexifyAPIError> {
+        let start_name = self.start_node.name();
+        if !self.nodes.contains_key(&start_name) {
+            return Err(IndexifyAPIError::bad_request(&format!(
+                "start node '{start_name}' is not present in nodes"
+            )));
+        }
+
+        for (from, targets) in &self.edges {
+            if !self.nodes.contains_key(from) {
+                return Err(IndexifyAPIError::bad_request(&format!(
+                    "edge references unknown source node '{from}'"
+                )));
+            }
+            for to in targets {
+                if !self.nodes.contains_key(to) {
+                    return Err(IndexifyAPIError::bad_request(&format!(
+                        "edge '{from}' -> '{to}' references unknown node '{to}'"
+                    )));
+                }
+            }
+        }
+
+        for node in self.nodes.values() {
+            if let Node::DynamicRouter(router) = node {
+                for target in &router.target_fns {
+                    if !self.nodes.contains_key(target) {
+                        return Err(IndexifyAPIError::bad_request(&format!(
+                            "dynamic router '{}' targets unknown node '{target}'",
+                            router.name
+                        )));
+                    }
+                }
+            }
+        }
+
+        self.check_acyclic()?;
+        self.check_reachable(&start_name)?;
+
+        Ok(())
+    }
+
+    fn check_acyclic(&self) -> Result<(), IndexifyAPIError> {
+        #[derive(Clone, Copy, PartialEq, Eq)]
+        enum Mark {
+            Visiting,
+            Done,
+        }
+
+        fn visit<'a>(
+            node: &'a str,
+            edges: &'a HashMap<String, Vec<String>>,
+            marks: &mut HashMap<&'a str, Mark>,
+        ) -> Result<(), IndexifyAPIError> {
+            match marks.get(node) {
+                Some(Mark::Done) => return Ok(()),
+                Some(Mark::Visiting) => {
+                    return Err(IndexifyAPIError::bad_request(&format!(
+                        "compute graph contains a cycle through node '{node}'"
+                    )));
+                }
+                None => {}
+            }
+            marks.insert(node, Mark::Visiting);
+            if let Some(targets) = edges.get(node) {
+                for target in targets {
+                    visit(target, edges, marks)?;
+                }
+            }
+            marks.insert(node, Mark::Done);
+            Ok(())
+        }
+
+        let mut marks = HashMap::new();
+        for name in self.nodes.keys() {
+            visit(name, &self.edges, &mut marks)?;
+        }
+        Ok(())
+    }
+
+    fn check_reachable(&self, start_name: &str) -> Result<(), IndexifyAPIError> {
+        let mut reachable: HashSet<&str> = HashSet::new();
+        let mut stack = vec![start_name];
+        while let Some(name) = stack.pop() {
+            if !reachable.insert(name) {
+                continue;
+            }
+            if let Some(targets) = self.edges.get(name) {
+                for target in targets {
+                    if !reachable.contains(target.as_str()) {
+                        stack.push(target);
+                    }
+                }
+            }
+            // A dynamic router reaches its `target_fns` without an entry in
+            // `edges`.
+            if let Some(Node::DynamicRouter(router)) = self.nodes.get(name) {
+                for target in &router.target_fns {
+                    if !reachable.contains(target.as_str()) {
+                        stack.push(target);
+                    }
+                }
+            }
+        }
+
+        let unreachable: Vec<&str> = self
+            .nodes
+            .keys()
+            .map(String::as_str)
+            .filter(|name| !reachable.contains(name))
+            .collect();
+        if !unreachable.is_empty() {
+            return Err(IndexifyAPIError::bad_request(&format!(
+                "unreachable node(s) from start node '{start_name}': {}",
+                unreachable.join(", ")
+            )));
+        }
+        Ok(())
+    }
+
     pub fn into_data_model(
         self,
         code_path: &str,
         sha256_hash: &str,
         size: u64,
     ) -> Result<data_model::ComputeGraph, IndexifyAPIError> {
+        self.validate()?;
         let mut nodes = HashMap::new();
         for (name, node) in self.nodes {
             nodes.insert(name, node.into());
         }
         let start_fn: data_model::Node = self.start_node.into();
         let compute_graph = data_model::ComputeGraph {
-            name: self.name,
-            namespace: self.namespace,
+            name: self.name.to_string(),
+            namespace: self.namespace.to_string(),
             description: self.description,
             start_fn,
             version: Default::default(),
@@ -245,8 +564,8 @@ impl From<data_model::ComputeGraph> for ComputeGraph {
             nodes.insert(k, v.into());
         }
         Self {
-            name: compute_graph.name,
-            namespace: compute_graph.namespace,
+            name: compute_graph.name.into(),
+            namespace: compute_graph.namespace.into(),
             description: compute_graph.description,
             start_node: start_fn,
             nodes,
@@ -258,7 +577,7 @@ impl From<data_model::ComputeGraph> for ComputeGraph {
 
 #[derive(Debug, Serialize, Deserialize, ToSchema)]
 pub struct CreateNamespace {
-    pub name: String,
+    pub name: NamespaceName,
 }
 
 #[derive(Debug, Serialize, Deserialize, ToSchema)]
@@ -269,24 +588,48 @@ pub struct ComputeGraphsList {
 
 #[derive(Debug, Serialize, Deserialize, ToSchema)]
 pub struct DataObject {
-    pub id: String,
+    pub id: OutputId,
     pub payload_size: u64,
     pub payload_sha_256: String,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct QueryParams {
-    pub input_id: Option<String>,
+    pub input_id: Option<OutputId>,
     pub on_graph_end: bool,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct GraphOutputNotification {
-    pub output_id: String,
-    pub compute_graph: String,
+    pub output_id: OutputId,
+    pub compute_graph: GraphName,
     pub fn_name: String,
 }
 
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct CreateSubscription {
+    pub compute_graph: GraphName,
+    pub fn_name: Option<String>,
+    pub callback_url: String,
+    pub secret: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct Subscription {
+    pub id: String,
+    pub namespace: NamespaceName,
+    pub compute_graph: GraphName,
+    pub fn_name: Option<String>,
+    pub callback_url: String,
+    pub created_at: u64,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct SubscriptionList {
+    pub subscriptions: Vec<Subscription>,
+    pub cursor: Option<Vec<u8>>,
+}
+
 #[derive(Debug, Serialize, Deserialize, ToSchema)]
 pub struct CreateNamespaceResponse {
     pub name: Namespace,
@@ -338,28 +681,28 @@ impl From<data_model::TaskOutcome> for TaskOutcome {
 
 #[derive(Debug, Serialize, Deserialize, ToSchema)]
 pub struct Task {
-    pub id: String,
-    pub namespace: String,
+    pub id: TaskId,
+    pub namespace: NamespaceName,
     pub compute_fn: String,
-    pub compute_graph: String,
-    pub invocation_id: String,
+    pub compute_graph: GraphName,
+    pub invocation_id: InvocationRef,
     pub input_key: String,
     pub outcome: TaskOutcome,
-    pub reducer_output_id: Option<String>,
+    pub reducer_output_id: Option<OutputId>,
     pub graph_version: GraphVersion,
 }
 
 impl From<data_model::Task> for Task {
     fn from(task: data_model::Task) -> Self {
         Self {
-            id: task.id.to_string(),
-            namespace: task.namespace,
+            id: task.id.to_string().into(),
+            namespace: task.namespace.into(),
             compute_fn: task.compute_fn_name,
-            compute_graph: task.compute_graph_name,
-            invocation_id: task.invocation_id,
+            compute_graph: task.compute_graph_name.into(),
+            invocation_id: task.invocation_id.into(),
             input_key: task.input_node_output_key,
             outcome: task.outcome.into(),
-            reducer_output_id: task.reducer_output_id,
+            reducer_output_id: task.reducer_output_id.map(Into::into),
             graph_version: task.graph_version,
         }
     }
@@ -374,14 +717,14 @@ pub struct Tasks {
 #[derive(Debug, Serialize, Deserialize, ToSchema)]
 pub struct FnOutput {
     pub compute_fn: String,
-    pub id: String,
+    pub id: OutputId,
 }
 
 impl From<data_model::NodeOutput> for FnOutput {
     fn from(output: data_model::NodeOutput) -> Self {
         Self {
             compute_fn: output.compute_fn_name,
-            id: output.id.to_string(),
+            id: output.id.to_string().into(),
         }
     }
 }
@@ -429,4 +772,88 @@ mod tests {
         json_value["namespace"] = serde_json::Value::String("test".to_string());
         let _: super::ComputeGraph = serde_json::from_value(json_value).unwrap();
     }
+
+    #[test]
+    fn test_compute_graph_validate_rejects_cycle() {
+        let json = r#"{"name":"test","namespace":"test","description":"test","start_node":{"compute_fn":{"name":"extractor_a","fn_name":"extractor_a","description":"", "reducer": false}},"nodes":{"extractor_a":{"compute_fn":{"name":"extractor_a","fn_name":"extractor_a","description":"", "reducer": false}},"extractor_b":{"compute_fn":{"name":"extractor_b","fn_name":"extractor_b","description":"", "reducer": false}}},"edges":{"extractor_a":["extractor_b"],"extractor_b":["extractor_a"]}}"#;
+        let graph: super::ComputeGraph = serde_json::from_str(json).unwrap();
+        assert!(graph.validate().is_err());
+    }
+
+    #[test]
+    fn test_compute_graph_validate_rejects_unreachable_node() {
+        let json = r#"{"name":"test","namespace":"test","description":"test","start_node":{"compute_fn":{"name":"extractor_a","fn_name":"extractor_a","description":"", "reducer": false}},"nodes":{"extractor_a":{"compute_fn":{"name":"extractor_a","fn_name":"extractor_a","description":"", "reducer": false}},"extractor_b":{"compute_fn":{"name":"extractor_b","fn_name":"extractor_b","description":"", "reducer": false}}},"edges":{}}"#;
+        let graph: super::ComputeGraph = serde_json::from_str(json).unwrap();
+        assert!(graph.validate().is_err());
+    }
+
+    #[test]
+    fn test_compute_graph_validate_accepts_well_formed_graph() {
+        let json = r#"{"name":"test","namespace":"test","description":"test","start_node":{"compute_fn":{"name":"extractor_a","fn_name":"extractor_a","description":"", "reducer": false}},"nodes":{"extractor_a":{"compute_fn":{"name":"extractor_a","fn_name":"extractor_a","description":"", "reducer": false}},"extractor_b":{"compute_fn":{"name":"extractor_b","fn_name":"extractor_b","description":"", "reducer": false}}},"edges":{"extractor_a":["extractor_b"]}}"#;
+        let graph: super::ComputeGraph = serde_json::from_str(json).unwrap();
+        assert!(graph.validate().is_ok());
+    }
+
+    fn sample_list_params() -> super::ListParams {
+        super::ListParams {
+            limit: Some(10),
+            cursor: Some(vec![0xde, 0xad, 0xbe, 0xef]),
+            filters: vec![super::FilterClause {
+                field: "outcome".to_string(),
+                operator: super::FilterOperator::Eq,
+                value: serde_json::json!("Failure"),
+            }],
+            sort: Some(super::SortClause {
+                field: "created_at".to_string(),
+                direction: super::SortDir::Desc,
+            }),
+        }
+    }
+
+    #[test]
+    fn test_list_params_validate_fields_rejects_unknown_filter_field() {
+        let params = sample_list_params();
+        let err = params.validate_fields(&["created_at"]).unwrap_err();
+        assert!(format!("{err:?}").contains("outcome"));
+    }
+
+    #[test]
+    fn test_list_params_validate_fields_rejects_unknown_sort_field() {
+        let params = sample_list_params();
+        let err = params.validate_fields(&["outcome"]).unwrap_err();
+        assert!(format!("{err:?}").contains("created_at"));
+    }
+
+    #[test]
+    fn test_list_params_validate_fields_accepts_allowed_fields() {
+        let params = sample_list_params();
+        assert!(params.validate_fields(&["outcome", "created_at"]).is_ok());
+    }
+
+    #[test]
+    fn test_list_params_to_query_string_round_trips_cursor_and_filters() {
+        let query = sample_list_params().to_query_string().unwrap();
+        assert!(query.contains("limit=10"));
+        assert!(query.contains("cursor=deadbeef"));
+        assert!(query.contains("sort_by=created_at"));
+        assert!(query.contains("sort_dir=desc"));
+
+        let parsed: std::collections::HashMap<String, String> =
+            serde_urlencoded::from_str(&query).expect("query string should parse");
+        let filters: Vec<super::FilterClause> =
+            serde_json::from_str(&parsed["filters"]).expect("filters should round-trip as JSON");
+        assert_eq!(filters.len(), 1);
+        assert_eq!(filters[0].field, "outcome");
+    }
+
+    #[test]
+    fn test_list_params_to_query_string_omits_unset_fields() {
+        let params = super::ListParams {
+            limit: None,
+            cursor: None,
+            filters: vec![],
+            sort: None,
+        };
+        assert_eq!(params.to_query_string().unwrap(), "");
+    }
 }
@@ -0,0 +1,51 @@
+use std::{convert::Infallible, fmt, str::FromStr};
+
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+/// Declares a `#[serde(transparent)]` newtype around a `String` id so that,
+/// for example, an invocation id can no longer be passed where a task id is
+/// expected, while still (de)serializing and rendering in OpenAPI schemas
+/// exactly like the bare `String` it replaces.
+macro_rules! id_newtype {
+    ($name:ident) => {
+        #[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize, ToSchema)]
+        #[serde(transparent)]
+        pub struct $name(pub String);
+
+        impl From<&str> for $name {
+            fn from(id: &str) -> Self {
+                Self(id.to_string())
+            }
+        }
+
+        impl From<String> for $name {
+            fn from(id: String) -> Self {
+                Self(id)
+            }
+        }
+
+        impl fmt::Display for $name {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                write!(f, "{}", self.0)
+            }
+        }
+
+        impl FromStr for $name {
+            type Err = Infallible;
+
+            fn from_str(id: &str) -> Result<Self, Self::Err> {
+                Ok(Self(id.to_string()))
+            }
+        }
+    };
+}
+
+// Named `InvocationRef` rather than `InvocationId` because `http_objects`
+// already exposes a public `InvocationId` response struct (`{ id: String }`);
+// reusing the name here would silently displace that existing API type.
+id_newtype!(InvocationRef);
+id_newtype!(TaskId);
+id_newtype!(OutputId);
+id_newtype!(NamespaceName);
+id_newtype!(GraphName);
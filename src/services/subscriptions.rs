@@ -0,0 +1,517 @@
+use std::{
+    collections::HashMap,
+    net::{IpAddr, Ipv6Addr},
+    sync::{Arc, RwLock},
+    time::Duration,
+};
+
+use axum::extract::{Path, State};
+use hmac::{Hmac, Mac};
+use indexify_utils::get_epoch_time_in_ms;
+use rand::RngCore;
+use sha2::Sha256;
+use tracing::warn;
+use url::Url;
+
+use crate::{
+    http_objects::{
+        CreateSubscription, GraphOutputNotification, IndexifyAPIError, Subscription,
+        SubscriptionList,
+    },
+    ids::{GraphName, NamespaceName},
+    services::auth::{Authenticated, NamespacePolicy, Role},
+};
+
+type HmacSha256 = Hmac<Sha256>;
+
+const MAX_DELIVERY_ATTEMPTS: u32 = 5;
+const INITIAL_BACKOFF: Duration = Duration::from_secs(1);
+
+/// A registered subscription together with the secret used to sign
+/// deliveries. This is the record persisted by the registry; the
+/// `secret` is intentionally left out of the public `Subscription` API
+/// type so it is never echoed back in a list/get response.
+#[derive(Debug, Clone)]
+pub struct SubscriptionRecord {
+    pub id: String,
+    pub namespace: NamespaceName,
+    pub compute_graph: GraphName,
+    pub fn_name: Option<String>,
+    pub callback_url: String,
+    pub secret: Option<String>,
+    pub created_at: u64,
+}
+
+impl From<SubscriptionRecord> for Subscription {
+    fn from(record: SubscriptionRecord) -> Self {
+        Self {
+            id: record.id,
+            namespace: record.namespace,
+            compute_graph: record.compute_graph,
+            fn_name: record.fn_name,
+            callback_url: record.callback_url,
+            created_at: record.created_at,
+        }
+    }
+}
+
+/// Generates an opaque subscription id, e.g. `sub_3f2c1a9b...`.
+pub fn new_subscription_id() -> String {
+    let mut bytes = [0u8; 16];
+    rand::thread_rng().fill_bytes(&mut bytes);
+    format!("sub_{}", hex::encode(bytes))
+}
+
+/// Signs a notification payload with the subscription's secret, producing
+/// the value sent in the `X-Indexify-Signature` header so subscribers can
+/// verify a delivery actually came from this server.
+pub fn sign_payload(secret: &str, payload: &[u8]) -> Result<String, IndexifyAPIError> {
+    let mut mac = HmacSha256::new_from_slice(secret.as_bytes())
+        .map_err(|e| IndexifyAPIError::internal_error_str(&e.to_string()))?;
+    mac.update(payload);
+    Ok(hex::encode(mac.finalize().into_bytes()))
+}
+
+/// Rejects `callback_url`s that would let a subscriber turn this server into
+/// an SSRF proxy: anything other than `http`/`https`, and any host that
+/// resolves to a loopback, link-local, or private address. This is a
+/// literal-only check (it does not follow redirects or resolve DNS), so it
+/// catches the obvious cases — `http://169.254.169.254/...`,
+/// `http://localhost/...`, `http://10.0.0.5/...` — without promising to stop
+/// a subscriber who points a public hostname at a private IP via DNS.
+fn validate_callback_url(callback_url: &str) -> Result<(), IndexifyAPIError> {
+    let url = Url::parse(callback_url)
+        .map_err(|e| IndexifyAPIError::bad_request(&format!("invalid callback_url: {e}")))?;
+
+    if url.scheme() != "http" && url.scheme() != "https" {
+        return Err(IndexifyAPIError::bad_request(
+            "callback_url must use the http or https scheme",
+        ));
+    }
+
+    let host = url
+        .host_str()
+        .ok_or_else(|| IndexifyAPIError::bad_request("callback_url must have a host"))?;
+
+    if host.eq_ignore_ascii_case("localhost") {
+        return Err(IndexifyAPIError::bad_request(
+            "callback_url must not target a loopback, link-local, or private address",
+        ));
+    }
+
+    // `host_str` keeps the brackets around an IPv6 literal, e.g. `[fc00::1]`.
+    if let Ok(ip) = host.trim_start_matches('[').trim_end_matches(']').parse::<IpAddr>() {
+        if is_disallowed_callback_ip(ip) {
+            return Err(IndexifyAPIError::bad_request(
+                "callback_url must not target a loopback, link-local, or private address",
+            ));
+        }
+    }
+
+    Ok(())
+}
+
+fn is_disallowed_callback_ip(ip: IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(v4) => {
+            v4.is_loopback()
+                || v4.is_link_local()
+                || v4.is_private()
+                || v4.is_unspecified()
+                || v4.is_multicast()
+                || v4.is_broadcast()
+        }
+        IpAddr::V6(v6) => {
+            v6.is_loopback()
+                || v6.is_unspecified()
+                || v6.is_multicast()
+                || is_unique_local_v6(&v6)
+                || is_unicast_link_local_v6(&v6)
+        }
+    }
+}
+
+/// `Ipv6Addr::is_unique_local` equivalent (fc00::/7); not yet stable on the
+/// std type, so checked against the address segments directly.
+fn is_unique_local_v6(v6: &Ipv6Addr) -> bool {
+    (v6.segments()[0] & 0xfe00) == 0xfc00
+}
+
+/// `Ipv6Addr::is_unicast_link_local` equivalent (fe80::/10); not yet stable
+/// on the std type, so checked against the address segments directly.
+fn is_unicast_link_local_v6(v6: &Ipv6Addr) -> bool {
+    (v6.segments()[0] & 0xffc0) == 0xfe80
+}
+
+/// Whether `record` should receive `notification`: the compute graph must
+/// match, and if the subscription was scoped to a single fn_name, that must
+/// match too.
+pub fn matches(record: &SubscriptionRecord, notification: &GraphOutputNotification) -> bool {
+    record.compute_graph == notification.compute_graph
+        && record
+            .fn_name
+            .as_deref()
+            .map(|f| f == notification.fn_name)
+            .unwrap_or(true)
+}
+
+/// Pushes `GraphOutputNotification`s to subscriber callback URLs, retrying
+/// failed deliveries with exponential backoff.
+pub struct Dispatcher {
+    client: reqwest::Client,
+}
+
+impl Default for Dispatcher {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Dispatcher {
+    pub fn new() -> Self {
+        Self {
+            client: reqwest::Client::new(),
+        }
+    }
+
+    /// Delivers `notification` to `record`'s callback URL, HMAC-signing the
+    /// body when the subscription has a secret. Gives up after
+    /// `MAX_DELIVERY_ATTEMPTS`, logging a warning for each failed attempt and
+    /// when it gives up; no error is returned to the caller.
+    pub async fn deliver(
+        &self,
+        record: &SubscriptionRecord,
+        notification: &GraphOutputNotification,
+    ) {
+        let body = match serde_json::to_vec(notification) {
+            Ok(body) => body,
+            Err(e) => {
+                warn!(
+                    "subscription {}: failed to serialize notification: {e}",
+                    record.id
+                );
+                return;
+            }
+        };
+
+        let mut backoff = INITIAL_BACKOFF;
+        for attempt in 1..=MAX_DELIVERY_ATTEMPTS {
+            let mut request = self
+                .client
+                .post(&record.callback_url)
+                .body(body.clone())
+                .header("Content-Type", "application/json");
+            if let Some(secret) = &record.secret {
+                match sign_payload(secret, &body) {
+                    Ok(signature) => {
+                        request = request.header("X-Indexify-Signature", signature);
+                    }
+                    Err(e) => warn!("subscription {}: failed to sign payload: {e:?}", record.id),
+                }
+            }
+
+            match request.send().await {
+                Ok(resp) if resp.status().is_success() => return,
+                Ok(resp) => warn!(
+                    "subscription {}: callback returned {} (attempt {attempt}/{MAX_DELIVERY_ATTEMPTS})",
+                    record.id,
+                    resp.status()
+                ),
+                Err(e) => warn!(
+                    "subscription {}: callback request failed: {e} (attempt {attempt}/{MAX_DELIVERY_ATTEMPTS})",
+                    record.id
+                ),
+            }
+
+            if attempt < MAX_DELIVERY_ATTEMPTS {
+                tokio::time::sleep(backoff).await;
+                backoff *= 2;
+            }
+        }
+
+        warn!(
+            "subscription {}: giving up after {MAX_DELIVERY_ATTEMPTS} delivery attempts",
+            record.id
+        );
+    }
+}
+
+/// In-memory, namespace-scoped store of `SubscriptionRecord`s, keyed by
+/// subscription id. Backs the register/list/delete handlers below and
+/// selects the matching subscriptions in `dispatch`.
+#[derive(Clone, Default)]
+pub struct SubscriptionRegistry {
+    records: Arc<RwLock<HashMap<String, SubscriptionRecord>>>,
+}
+
+impl SubscriptionRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn register(
+        &self,
+        namespace: NamespaceName,
+        create: CreateSubscription,
+    ) -> Result<Subscription, IndexifyAPIError> {
+        validate_callback_url(&create.callback_url)?;
+
+        let record = SubscriptionRecord {
+            id: new_subscription_id(),
+            namespace,
+            compute_graph: create.compute_graph,
+            fn_name: create.fn_name,
+            callback_url: create.callback_url,
+            secret: create.secret,
+            created_at: get_epoch_time_in_ms(),
+        };
+        let subscription = Subscription::from(record.clone());
+        self.records
+            .write()
+            .expect("subscription registry lock poisoned")
+            .insert(record.id.clone(), record);
+        Ok(subscription)
+    }
+
+    pub fn list(&self, namespace: &NamespaceName) -> Vec<Subscription> {
+        self.records
+            .read()
+            .expect("subscription registry lock poisoned")
+            .values()
+            .filter(|record| &record.namespace == namespace)
+            .cloned()
+            .map(Subscription::from)
+            .collect()
+    }
+
+    pub fn delete(&self, namespace: &NamespaceName, id: &str) -> Result<(), IndexifyAPIError> {
+        let mut records = self
+            .records
+            .write()
+            .expect("subscription registry lock poisoned");
+        match records.get(id) {
+            Some(record) if &record.namespace == namespace => {
+                records.remove(id);
+                Ok(())
+            }
+            _ => Err(IndexifyAPIError::not_found(&format!(
+                "subscription '{id}' not found in namespace '{namespace}'"
+            ))),
+        }
+    }
+
+    /// Delivers `notification` to every subscription registered for
+    /// `namespace` that matches it, one subscription after another. Nothing
+    /// in this crate calls `dispatch` when a graph invocation produces an
+    /// output; notifications are sent only where a caller invokes it.
+    pub async fn dispatch(
+        &self,
+        dispatcher: &Dispatcher,
+        namespace: &NamespaceName,
+        notification: &GraphOutputNotification,
+    ) {
+        let matching: Vec<SubscriptionRecord> = self
+            .records
+            .read()
+            .expect("subscription registry lock poisoned")
+            .values()
+            .filter(|record| &record.namespace == namespace && matches(record, notification))
+            .cloned()
+            .collect();
+
+        for record in matching {
+            dispatcher.deliver(&record, notification).await;
+        }
+    }
+}
+
+/// Shared axum state for the subscription handlers below.
+#[derive(Clone)]
+pub struct SubscriptionsState {
+    pub registry: SubscriptionRegistry,
+    pub dispatcher: Arc<Dispatcher>,
+}
+
+pub async fn create_subscription(
+    State(state): State<SubscriptionsState>,
+    Path(namespace): Path<NamespaceName>,
+    Authenticated(claims): Authenticated,
+    axum::Json(payload): axum::Json<CreateSubscription>,
+) -> Result<axum::Json<Subscription>, IndexifyAPIError> {
+    NamespacePolicy::new(namespace.clone(), Role::Writer).check(&claims)?;
+    Ok(axum::Json(state.registry.register(namespace, payload)?))
+}
+
+pub async fn list_subscriptions(
+    State(state): State<SubscriptionsState>,
+    Path(namespace): Path<NamespaceName>,
+    Authenticated(claims): Authenticated,
+) -> Result<axum::Json<SubscriptionList>, IndexifyAPIError> {
+    NamespacePolicy::new(namespace.clone(), Role::Reader).check(&claims)?;
+    Ok(axum::Json(SubscriptionList {
+        subscriptions: state.registry.list(&namespace),
+        cursor: None,
+    }))
+}
+
+pub async fn delete_subscription(
+    State(state): State<SubscriptionsState>,
+    Path((namespace, id)): Path<(NamespaceName, String)>,
+    Authenticated(claims): Authenticated,
+) -> Result<(), IndexifyAPIError> {
+    NamespacePolicy::new(namespace.clone(), Role::Writer).check(&claims)?;
+    state.registry.delete(&namespace, &id)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample_record() -> SubscriptionRecord {
+        SubscriptionRecord {
+            id: "sub_test".to_string(),
+            namespace: NamespaceName::from("ns"),
+            compute_graph: GraphName::from("graph_a"),
+            fn_name: Some("extractor_a".to_string()),
+            callback_url: "http://localhost/callback".to_string(),
+            secret: Some("shh".to_string()),
+            created_at: 0,
+        }
+    }
+
+    fn notification_for(compute_graph: &str, fn_name: &str) -> GraphOutputNotification {
+        GraphOutputNotification {
+            output_id: "out_1".to_string().into(),
+            compute_graph: GraphName::from(compute_graph),
+            fn_name: fn_name.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_new_subscription_id_has_prefix() {
+        let id = new_subscription_id();
+        assert!(id.starts_with("sub_"));
+        assert_ne!(new_subscription_id(), new_subscription_id());
+    }
+
+    #[test]
+    fn test_sign_payload_is_deterministic_and_key_dependent() {
+        let a = sign_payload("secret", b"payload").unwrap();
+        let b = sign_payload("secret", b"payload").unwrap();
+        assert_eq!(a, b);
+        assert_ne!(a, sign_payload("other-secret", b"payload").unwrap());
+    }
+
+    #[test]
+    fn test_matches_requires_same_compute_graph() {
+        let record = sample_record();
+        assert!(!matches(
+            &record,
+            &notification_for("graph_b", "extractor_a")
+        ));
+    }
+
+    #[test]
+    fn test_matches_respects_fn_name_scope() {
+        let record = sample_record();
+        assert!(matches(
+            &record,
+            &notification_for("graph_a", "extractor_a")
+        ));
+        assert!(!matches(
+            &record,
+            &notification_for("graph_a", "extractor_b")
+        ));
+    }
+
+    #[test]
+    fn test_matches_unscoped_subscription_accepts_any_fn_name() {
+        let mut record = sample_record();
+        record.fn_name = None;
+        assert!(matches(
+            &record,
+            &notification_for("graph_a", "extractor_z")
+        ));
+    }
+
+    #[test]
+    fn test_registry_register_list_delete_round_trip() {
+        let registry = SubscriptionRegistry::new();
+        let namespace = NamespaceName::from("ns");
+        let other_namespace = NamespaceName::from("other-ns");
+
+        let subscription = registry
+            .register(
+                namespace.clone(),
+                CreateSubscription {
+                    compute_graph: GraphName::from("graph_a"),
+                    fn_name: None,
+                    callback_url: "https://example.com/callback".to_string(),
+                    secret: None,
+                },
+            )
+            .unwrap();
+
+        assert_eq!(registry.list(&namespace).len(), 1);
+        assert!(registry.list(&other_namespace).is_empty());
+
+        // A namespace can't delete another namespace's subscription.
+        assert!(registry.delete(&other_namespace, &subscription.id).is_err());
+
+        registry.delete(&namespace, &subscription.id).unwrap();
+        assert!(registry.list(&namespace).is_empty());
+    }
+
+    #[test]
+    fn test_register_rejects_loopback_callback_url() {
+        let registry = SubscriptionRegistry::new();
+        let err = registry
+            .register(
+                NamespaceName::from("ns"),
+                CreateSubscription {
+                    compute_graph: GraphName::from("graph_a"),
+                    fn_name: None,
+                    callback_url: "http://127.0.0.1/callback".to_string(),
+                    secret: None,
+                },
+            )
+            .unwrap_err();
+        assert!(format!("{err:?}").contains("loopback"));
+    }
+
+    #[test]
+    fn test_validate_callback_url_accepts_public_https_host() {
+        assert!(validate_callback_url("https://example.com/callback").is_ok());
+    }
+
+    #[test]
+    fn test_validate_callback_url_rejects_non_http_scheme() {
+        assert!(validate_callback_url("ftp://example.com/callback").is_err());
+    }
+
+    #[test]
+    fn test_validate_callback_url_rejects_localhost() {
+        assert!(validate_callback_url("http://localhost/callback").is_err());
+    }
+
+    #[test]
+    fn test_validate_callback_url_rejects_loopback_ip() {
+        assert!(validate_callback_url("http://127.0.0.1/callback").is_err());
+    }
+
+    #[test]
+    fn test_validate_callback_url_rejects_link_local_ip() {
+        assert!(validate_callback_url("http://169.254.169.254/latest").is_err());
+    }
+
+    #[test]
+    fn test_validate_callback_url_rejects_private_ip() {
+        assert!(validate_callback_url("http://10.0.0.5/callback").is_err());
+        assert!(validate_callback_url("http://192.168.1.1/callback").is_err());
+    }
+
+    #[test]
+    fn test_validate_callback_url_rejects_ipv6_unique_local() {
+        assert!(validate_callback_url("http://[fc00::1]/callback").is_err());
+    }
+}